@@ -5,6 +5,9 @@ use std::num::NonZeroUsize;
 use lsp::Connection;
 use lsp_server as lsp;
 use lsp_types as types;
+use types::notification::Cancel;
+use types::notification::DidChangeWatchedFiles;
+use types::notification::Notification as _;
 use types::ClientCapabilities;
 use types::CodeActionKind;
 use types::CodeActionOptions;
@@ -27,6 +30,7 @@ use crate::PositionEncoding;
 mod api;
 mod client;
 mod schedule;
+mod watcher;
 
 pub(crate) type Result<T> = std::result::Result<T, api::Error>;
 
@@ -100,11 +104,41 @@ impl Server {
         mut session: Session,
         worker_threads: NonZeroUsize,
     ) -> crate::Result<()> {
-        let mut scheduler =
-            schedule::Scheduler::new(&mut session, worker_threads, &connection.sender);
+        let workspace_roots = session.workspace_roots();
+        let mut scheduler = schedule::Scheduler::new(
+            &mut session,
+            client_capabilities,
+            worker_threads,
+            &connection.sender,
+        );
+
+        let client_watches_config_files =
+            Self::try_register_capabilities(client_capabilities, &mut scheduler);
+        let fs_watcher = (!client_watches_config_files)
+            .then(|| Self::spawn_fallback_watcher(&workspace_roots))
+            .flatten();
+
+        loop {
+            let msg = match &fs_watcher {
+                Some(fs_watcher) => crossbeam_channel::select! {
+                    recv(connection.receiver) -> msg => match msg {
+                        Ok(msg) => msg,
+                        Err(_) => break,
+                    },
+                    recv(fs_watcher.changes()) -> changes => match changes {
+                        Ok(params) => lsp::Message::Notification(lsp::Notification::new(
+                            DidChangeWatchedFiles::METHOD.to_string(),
+                            params,
+                        )),
+                        Err(_) => continue,
+                    },
+                },
+                None => match connection.receiver.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+            };
 
-        Self::try_register_capabilities(client_capabilities, &mut scheduler);
-        for msg in &connection.receiver {
             let task = match msg {
                 lsp::Message::Request(req) => {
                     if connection.handle_shutdown(&req)? {
@@ -112,7 +146,21 @@ impl Server {
                     }
                     api::request(req)
                 }
-                lsp::Message::Notification(notification) => api::notification(notification),
+                lsp::Message::Notification(notification) => {
+                    if notification.method == Cancel::METHOD {
+                        match serde_json::from_value(notification.params) {
+                            Ok(params) => scheduler.cancel(params),
+                            Err(err) => {
+                                tracing::error!(
+                                    "Failed to deserialize `{}` params: {err}",
+                                    Cancel::METHOD
+                                );
+                            }
+                        }
+                        continue;
+                    }
+                    api::notification(notification)
+                }
                 lsp::Message::Response(response) => scheduler.response(response),
             };
             scheduler.dispatch(task);
@@ -120,10 +168,34 @@ impl Server {
         Ok(())
     }
 
+    /// Spawns the server-owned configuration-file watcher used when the client can't (or
+    /// didn't) register its own `workspace/didChangeWatchedFiles` watchers. Returns `None` if
+    /// the watcher fails to start, in which case config reloads simply won't happen.
+    fn spawn_fallback_watcher(
+        workspace_roots: &[std::path::PathBuf],
+    ) -> Option<watcher::ServerWatcher> {
+        match watcher::ServerWatcher::spawn(workspace_roots.to_vec()) {
+            Ok(fs_watcher) => {
+                tracing::info!(
+                    "Falling back to a server-owned filesystem watcher for configuration files"
+                );
+                Some(fs_watcher)
+            }
+            Err(err) => {
+                tracing::error!("Failed to start the fallback filesystem watcher: {err}");
+                None
+            }
+        }
+    }
+
+    /// Attempts to register the config-file watcher through the client's dynamic capability
+    /// registration. Returns `true` only if the registration request was actually sent
+    /// successfully, so the caller can decide whether the server-owned fallback watcher is
+    /// needed instead - we never want both running at once, nor neither.
     fn try_register_capabilities(
         client_capabilities: &ClientCapabilities,
         scheduler: &mut Scheduler,
-    ) {
+    ) -> bool {
         let dynamic_registration = client_capabilities
             .workspace
             .as_ref()
@@ -166,13 +238,18 @@ impl Server {
                 Task::nothing()
             };
 
-            if let Err(err) = scheduler
+            match scheduler
                 .request::<lsp_types::request::RegisterCapability>(params, response_handler)
             {
-                tracing::error!("An error occurred when trying to register the configuration file watcher: {err}");
+                Ok(()) => true,
+                Err(err) => {
+                    tracing::error!("An error occurred when trying to register the configuration file watcher: {err}; falling back to a server-owned filesystem watcher.");
+                    false
+                }
             }
         } else {
-            tracing::warn!("LSP client does not support dynamic capability registration - automatic configuration reloading will not be available.");
+            tracing::warn!("LSP client does not support dynamic capability registration - falling back to a server-owned filesystem watcher.");
+            false
         }
     }
 
@@ -188,6 +265,11 @@ impl Server {
                     .max() // this selects the highest priority position encoding
             })
             .unwrap_or_default();
+        let supports_workspace_diagnostics = client_capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.diagnostic.as_ref())
+            .is_some();
         types::ServerCapabilities {
             position_encoding: Some(position_encoding.into()),
             code_action_provider: Some(types::CodeActionProviderCapability::Options(
@@ -217,7 +299,8 @@ impl Server {
                     identifier: Some(crate::DIAGNOSTIC_NAME.into()),
                     // multi-file analysis could change this
                     inter_file_dependencies: false,
-                    workspace_diagnostics: false,
+                    // only advertised when the client asked for pull-based workspace diagnostics
+                    workspace_diagnostics: supports_workspace_diagnostics,
                     work_done_progress_options: WorkDoneProgressOptions {
                         work_done_progress: Some(true),
                     },
@@ -249,7 +332,6 @@ pub(crate) enum SupportedCodeAction {
     SourceFixAll,
     /// Maps to `source.organizeImports` and `source.organizeImports.ruff` code action kinds.
     /// This is a source action that applies import sorting fixes to the currently open document.
-    #[allow(dead_code)] // TODO: remove
     SourceOrganizeImports,
 }
 
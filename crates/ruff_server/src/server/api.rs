@@ -0,0 +1,67 @@
+//! Entry points for turning incoming `lsp::Request`s and `lsp::Notification`s into [`Task`]s
+//! the [`Scheduler`](super::schedule::Scheduler) can run.
+
+use lsp_server as lsp;
+
+mod notifications;
+mod requests;
+
+use super::schedule::Task;
+
+/// Dispatches an incoming request to its handler, producing a [`Task`] describing how (and
+/// where) the work should run.
+pub(super) fn request(req: lsp::Request) -> Task {
+    requests::request(req)
+}
+
+/// Dispatches an incoming notification to its handler, producing a [`Task`] describing how
+/// the work should run.
+///
+/// Note that `$/cancelRequest` never reaches this function: the event loop intercepts it
+/// directly so cancellation doesn't have to round-trip through dispatch.
+pub(super) fn notification(notification: lsp::Notification) -> Task {
+    notifications::notification(notification)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error("Unsupported method `{0}`")]
+    MethodNotFound(String),
+    #[error("`{0}` is not implemented in this build")]
+    NotImplemented(String),
+    #[error(transparent)]
+    Anyhow(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// Converts this error into an LSP error response for the given request id.
+    pub(super) fn into_response_error(self) -> lsp::ResponseError {
+        match self {
+            Error::MethodNotFound(method) => lsp::ResponseError {
+                code: lsp::ErrorCode::MethodNotFound as i32,
+                message: format!("Unsupported method `{method}`"),
+                data: None,
+            },
+            Error::NotImplemented(feature) => lsp::ResponseError {
+                code: lsp::ErrorCode::MethodNotFound as i32,
+                message: format!("`{feature}` is not implemented in this build"),
+                data: None,
+            },
+            Error::Anyhow(err) => lsp::ResponseError {
+                code: lsp::ErrorCode::InternalError as i32,
+                message: err.to_string(),
+                data: None,
+            },
+        }
+    }
+}
+
+/// Builds the error response a handler should reply with for a feature that isn't backed by a
+/// real implementation yet, instead of silently succeeding with an empty or no-op result.
+pub(crate) fn not_implemented_response(
+    id: lsp::RequestId,
+    feature: impl Into<String>,
+) -> lsp::Response {
+    let err = Error::NotImplemented(feature.into()).into_response_error();
+    lsp::Response::new_err(id, err.code, err.message)
+}
@@ -0,0 +1,15 @@
+use lsp_types as types;
+
+use crate::server::schedule::Task;
+
+/// Records the document's new version and bumps its diagnostic result id, leaving every other
+/// document's cached state untouched. No parsed AST or per-document settings are cached, so
+/// nothing else is evicted.
+pub(super) fn did_change(params: types::DidChangeTextDocumentParams) -> Task {
+    Task::local(move |session, _client| {
+        let url = params.text_document.uri;
+        session.set_document_version(&url, params.text_document.version);
+        session.invalidate(&url);
+        Task::nothing()
+    })
+}
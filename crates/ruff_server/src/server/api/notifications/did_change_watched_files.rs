@@ -0,0 +1,14 @@
+use lsp_types as types;
+
+use crate::server::schedule::Task;
+
+/// Invalidates the resolved settings for whichever workspace folder owns the changed config
+/// file, rather than dropping every workspace's cached settings.
+pub(super) fn did_change_watched_files(params: types::DidChangeWatchedFilesParams) -> Task {
+    Task::local(move |session, _client| {
+        for change in params.changes {
+            session.invalidate_settings_for(&change.uri);
+        }
+        Task::nothing()
+    })
+}
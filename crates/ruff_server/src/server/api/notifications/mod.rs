@@ -0,0 +1,43 @@
+use lsp_server as lsp;
+use lsp_types as types;
+use types::notification::DidChangeTextDocument;
+use types::notification::DidChangeWatchedFiles;
+use types::notification::Notification as _;
+
+use crate::server::schedule::Task;
+
+mod did_change;
+mod did_change_watched_files;
+
+/// Builds the [`Task`] for an incoming `lsp::Notification`.
+///
+/// `$/cancelRequest` is handled separately by the event loop and never reaches this function.
+pub(super) fn notification(notification: lsp::Notification) -> Task {
+    match notification.method.as_str() {
+        DidChangeTextDocument::METHOD => match serde_json::from_value(notification.params) {
+            Ok(params) => did_change::did_change(params),
+            Err(err) => {
+                tracing::error!(
+                    "Failed to deserialize `{}` params: {err}",
+                    notification.method
+                );
+                Task::nothing()
+            }
+        },
+        DidChangeWatchedFiles::METHOD => match serde_json::from_value(notification.params) {
+            Ok(params) => did_change_watched_files::did_change_watched_files(params),
+            Err(err) => {
+                tracing::error!(
+                    "Failed to deserialize `{}` params: {err}",
+                    notification.method
+                );
+                Task::nothing()
+            }
+        },
+        // Other notification handlers are added here as they're implemented.
+        method => {
+            tracing::debug!("Ignoring unknown notification `{method}`");
+            Task::nothing()
+        }
+    }
+}
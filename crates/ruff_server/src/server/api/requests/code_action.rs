@@ -0,0 +1,74 @@
+use lsp_server as lsp;
+use lsp_types as types;
+
+use super::fix_all;
+use super::organize_imports;
+use crate::server::schedule::Task;
+use crate::server::SupportedCodeAction;
+
+pub(super) fn code_action(id: lsp::RequestId, params: types::CodeActionParams) -> Task {
+    Task::local(move |session, client| {
+        let url = params.text_document.uri;
+        let requested = requested_kinds(params.context.only);
+
+        let mut actions = Vec::new();
+        if requested.contains(&SupportedCodeAction::SourceOrganizeImports) {
+            actions.push(types::CodeActionOrCommand::CodeAction(
+                organize_imports::code_action(session, &url),
+            ));
+        }
+        if requested.contains(&SupportedCodeAction::SourceFixAll) {
+            // The action carries no edit; its `codeAction/resolve` runs on a background worker
+            // with work-done progress, so it never blocks the initial `codeAction` response.
+            actions.push(types::CodeActionOrCommand::CodeAction(
+                fix_all::code_action(&url),
+            ));
+        }
+
+        client.respond(lsp::Response::new_ok(id, actions));
+        Task::nothing()
+    })
+}
+
+/// Which of our supported code action kinds the client is asking for. Editors only ever request
+/// these source actions explicitly (via a command like "Organize Imports"), so a missing filter
+/// is treated as "no source actions", not "all of them".
+fn requested_kinds(only: Option<Vec<types::CodeActionKind>>) -> Vec<SupportedCodeAction> {
+    only.into_iter()
+        .flatten()
+        .filter_map(|kind| SupportedCodeAction::try_from(kind).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::requested_kinds;
+    use crate::server::SupportedCodeAction;
+    use lsp_types as types;
+
+    #[test]
+    fn no_filter_requests_nothing() {
+        assert_eq!(requested_kinds(None), Vec::new());
+    }
+
+    #[test]
+    fn recognizes_supported_kinds() {
+        let only = Some(vec![
+            types::CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+            types::CodeActionKind::SOURCE_FIX_ALL,
+        ]);
+        assert_eq!(
+            requested_kinds(only),
+            vec![
+                SupportedCodeAction::SourceOrganizeImports,
+                SupportedCodeAction::SourceFixAll,
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_unsupported_kinds() {
+        let only = Some(vec![types::CodeActionKind::REFACTOR]);
+        assert_eq!(requested_kinds(only), Vec::new());
+    }
+}
@@ -0,0 +1,30 @@
+use lsp_server as lsp;
+use lsp_types as types;
+
+use super::fix_all;
+use super::organize_imports;
+use crate::server::schedule::Task;
+use crate::server::SupportedCodeAction;
+
+pub(super) fn code_action_resolve(id: lsp::RequestId, action: types::CodeAction) -> Task {
+    match action
+        .kind
+        .clone()
+        .and_then(|kind| SupportedCodeAction::try_from(kind).ok())
+    {
+        // `source.fixAll` can be slow enough to want work-done progress, so it resolves on a
+        // background worker. `codeAction/resolve` has no `workDoneToken` of its own, so the
+        // scheduler creates a fresh one rather than reusing one from the initial `codeAction` call.
+        Some(SupportedCodeAction::SourceFixAll) => fix_all::resolve(id, action, None),
+        Some(SupportedCodeAction::SourceOrganizeImports) => Task::local(move |session, client| {
+            let mut action = action;
+            organize_imports::resolve(session, &mut action);
+            client.respond(lsp::Response::new_ok(id, action));
+            Task::nothing()
+        }),
+        Some(SupportedCodeAction::QuickFix) | None => Task::local(move |_session, client| {
+            client.respond(lsp::Response::new_ok(id, action));
+            Task::nothing()
+        }),
+    }
+}
@@ -0,0 +1,73 @@
+use lsp_server as lsp;
+use lsp_types as types;
+
+use crate::server::api::not_implemented_response;
+use crate::server::schedule::cancelled_response;
+use crate::server::schedule::CancellationToken;
+use crate::server::schedule::ProgressReporter;
+use crate::server::schedule::Task;
+
+/// Builds the (data-only) `source.fixAll` code action. It carries no edit; its
+/// `codeAction/resolve` goes to [`resolve`], which runs on a background task so it can report
+/// work-done progress and be cancelled.
+pub(super) fn code_action(url: &types::Url) -> types::CodeAction {
+    types::CodeAction {
+        title: "Ruff: Fix all auto-fixable problems".to_string(),
+        kind: Some(types::CodeActionKind::SOURCE_FIX_ALL),
+        data: Some(serde_json::json!({ "uri": url })),
+        ..Default::default()
+    }
+}
+
+/// Handles `codeAction/resolve` for a `source.fixAll` action on a background worker, reporting
+/// work-done progress and honoring cancellation. No `WorkspaceEdit` is produced: an action with
+/// a `uri` in its data gets a "not implemented" error response, and one without is returned
+/// unchanged.
+pub(super) fn resolve(
+    id: lsp::RequestId,
+    action: types::CodeAction,
+    client_token: Option<types::ProgressToken>,
+) -> Task {
+    let response_id = id.clone();
+    Task::background_with_progress(
+        id,
+        "Ruff: Fix all auto-fixable problems",
+        client_token,
+        move |cancel_token, progress| {
+            let Some(_url) = action_url(&action) else {
+                return lsp::Response::new_ok(response_id, action);
+            };
+            if run_progress_chunks(&cancel_token, &progress) {
+                cancelled_response(response_id)
+            } else {
+                not_implemented_response(response_id, "source.fixAll")
+            }
+        },
+    )
+}
+
+fn action_url(action: &types::CodeAction) -> Option<types::Url> {
+    action
+        .data
+        .as_ref()?
+        .get("uri")?
+        .as_str()
+        .and_then(|uri| types::Url::parse(uri).ok())
+}
+
+/// Reports progress in chunks so the cancellation/progress contract a real safe-fix pass will
+/// run through is already exercised, even though no pass exists in this tree yet. Returns `true`
+/// if the request was cancelled partway through.
+///
+/// TODO: wire up to `ruff_linter`'s safe-fix pass once the rest of the diagnostics pipeline
+/// lands; until then this never actually produces an edit, see [`not_implemented_response`].
+fn run_progress_chunks(cancel_token: &CancellationToken, progress: &ProgressReporter) -> bool {
+    const CHUNKS: u32 = 10;
+    for chunk in 0..CHUNKS {
+        if cancel_token.is_cancelled() {
+            return true;
+        }
+        progress.report(chunk * 100 / CHUNKS, "Applying safe fixes");
+    }
+    false
+}
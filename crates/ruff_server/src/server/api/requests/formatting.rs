@@ -0,0 +1,44 @@
+use lsp_server as lsp;
+use lsp_types as types;
+
+use crate::server::api::not_implemented_response;
+use crate::server::schedule::cancelled_response;
+use crate::server::schedule::CancellationToken;
+use crate::server::schedule::ProgressReporter;
+use crate::server::schedule::Task;
+
+/// Handles `textDocument/formatting` on a background worker, reporting work-done progress (and
+/// honoring cancellation) so formatting a large file doesn't look like the server has hung.
+pub(super) fn formatting(id: lsp::RequestId, params: types::DocumentFormattingParams) -> Task {
+    let response_id = id.clone();
+    let client_token = params.work_done_progress_params.work_done_token;
+    Task::background_with_progress(
+        id,
+        format!("Ruff: Formatting {}", params.text_document.uri),
+        client_token,
+        move |cancel_token, progress| {
+            if run_progress_chunks(&cancel_token, &progress) {
+                cancelled_response(response_id)
+            } else {
+                not_implemented_response(response_id, "textDocument/formatting")
+            }
+        },
+    )
+}
+
+/// Reports progress in chunks so the cancellation/progress contract a real formatting pass will
+/// run through is already exercised, even though no pass exists in this tree yet. Returns `true`
+/// if the request was cancelled partway through.
+///
+/// TODO: wire up to `ruff_formatter` once the rest of the formatting pipeline lands; until then
+/// this never actually produces an edit, see [`not_implemented_response`].
+fn run_progress_chunks(cancel_token: &CancellationToken, progress: &ProgressReporter) -> bool {
+    const CHUNKS: u32 = 10;
+    for chunk in 0..CHUNKS {
+        if cancel_token.is_cancelled() {
+            return true;
+        }
+        progress.report(chunk * 100 / CHUNKS, "Formatting");
+    }
+    false
+}
@@ -0,0 +1,54 @@
+use lsp_server as lsp;
+use lsp_types as types;
+use types::request::CodeActionRequest;
+use types::request::CodeActionResolveRequest;
+use types::request::Formatting;
+use types::request::Request as _;
+use types::request::WorkspaceDiagnosticRequest;
+
+use super::Error;
+use crate::server::schedule::CancellationToken;
+use crate::server::schedule::ProgressReporter;
+use crate::server::schedule::Task;
+
+mod code_action;
+mod code_action_resolve;
+mod fix_all;
+mod formatting;
+mod organize_imports;
+mod workspace_diagnostic;
+
+/// Builds the [`Task`] for an incoming `lsp::Request`.
+pub(super) fn request(req: lsp::Request) -> Task {
+    let id = req.id.clone();
+    match req.method.as_str() {
+        WorkspaceDiagnosticRequest::METHOD => match serde_json::from_value(req.params) {
+            Ok(params) => workspace_diagnostic::workspace_diagnostic(id, params),
+            Err(err) => method_error(id, Error::Anyhow(err.into())),
+        },
+        CodeActionRequest::METHOD => match serde_json::from_value(req.params) {
+            Ok(params) => code_action::code_action(id, params),
+            Err(err) => method_error(id, Error::Anyhow(err.into())),
+        },
+        CodeActionResolveRequest::METHOD => match serde_json::from_value(req.params) {
+            Ok(action) => code_action_resolve::code_action_resolve(id, action),
+            Err(err) => method_error(id, Error::Anyhow(err.into())),
+        },
+        Formatting::METHOD => match serde_json::from_value(req.params) {
+            Ok(params) => formatting::formatting(id, params),
+            Err(err) => method_error(id, Error::Anyhow(err.into())),
+        },
+        // Other request handlers are added here as they're implemented.
+        method => method_error(id, Error::MethodNotFound(method.to_string())),
+    }
+}
+
+fn method_error(id: lsp::RequestId, err: Error) -> Task {
+    let err = err.into_response_error();
+    Task::background(
+        id.clone(),
+        move |_: CancellationToken, _: ProgressReporter| {
+            lsp::Response::new_err(id, err.code, err.message)
+        },
+    )
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use lsp_server as lsp;
+use lsp_types as types;
+
+use crate::server::schedule::cancelled_response;
+use crate::server::schedule::CancellationToken;
+use crate::server::schedule::ProgressReporter;
+use crate::server::schedule::Task;
+
+/// A Python file's diagnostics-relevant state, read out of [`Session`](crate::session::Session)
+/// on the main thread before handing the rest of the work to a background worker -
+/// `Task::Background` closures are `Send + 'static` and can't hold a reference to `Session`,
+/// which the event-loop thread owns exclusively.
+struct FileState {
+    url: types::Url,
+    version: Option<i32>,
+    result_id: String,
+}
+
+/// Handles `workspace/diagnostic`. The session read (the list of known Python files, plus each
+/// one's open version and diagnostic result id) is cheap and runs synchronously; the actual
+/// per-file lint computation - the part that scales with project size - runs on a background
+/// worker, reporting work-done progress and honoring cancellation the same way `source.fixAll`
+/// and formatting do, so a large workspace can't block the event loop or go uncancellable.
+pub(super) fn workspace_diagnostic(
+    id: lsp::RequestId,
+    params: types::WorkspaceDiagnosticParams,
+) -> Task {
+    Task::local(move |session, _client| {
+        let previous_result_ids: HashMap<_, _> = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect();
+
+        let files: Vec<FileState> = session
+            .workspace_python_files()
+            .into_iter()
+            .map(|url| {
+                let version = session.document_version(&url);
+                let result_id = session.diagnostic_result_id(&url);
+                FileState {
+                    url,
+                    version,
+                    result_id,
+                }
+            })
+            .collect();
+
+        let response_id = id.clone();
+        let client_token = params.work_done_progress_params.work_done_token;
+        Task::background_with_progress(
+            id,
+            "Ruff: Computing workspace diagnostics",
+            client_token,
+            move |cancel_token, progress| match compute_report(
+                files,
+                &previous_result_ids,
+                &cancel_token,
+                &progress,
+            ) {
+                Some(report) => lsp::Response::new_ok(response_id, report),
+                None => cancelled_response(response_id),
+            },
+        )
+    })
+}
+
+/// Builds the workspace diagnostic report, checking `cancel_token` between files so a large
+/// workspace can be cancelled partway through instead of only after every file is done.
+fn compute_report(
+    files: Vec<FileState>,
+    previous_result_ids: &HashMap<types::Url, String>,
+    cancel_token: &CancellationToken,
+    progress: &ProgressReporter,
+) -> Option<types::WorkspaceDiagnosticReportResult> {
+    let total = files.len() as u32;
+    let mut items = Vec::with_capacity(files.len());
+    for (done, file) in files.into_iter().enumerate() {
+        if cancel_token.is_cancelled() {
+            return None;
+        }
+        if total > 0 {
+            progress.report(done as u32 * 100 / total, "Computing diagnostics");
+        }
+
+        let FileState {
+            url,
+            version,
+            result_id,
+        } = file;
+        let item = if previous_result_ids.get(&url) == Some(&result_id) {
+            types::WorkspaceDocumentDiagnosticReport::Unchanged(
+                types::WorkspaceUnchangedDocumentDiagnosticReport {
+                    uri: url,
+                    version,
+                    unchanged_document_diagnostic_report:
+                        types::UnchangedDocumentDiagnosticReport { result_id },
+                },
+            )
+        } else {
+            // `diagnostics_for` doesn't actually have a lint pass behind it yet; see its doc
+            // comment. Computing it here (rather than alongside the session read above) keeps
+            // the slow/cancellable part of the work off the main thread once a real pass lands.
+            let diagnostics = crate::session::diagnostics_for(&url);
+            types::WorkspaceDocumentDiagnosticReport::Full(
+                types::WorkspaceFullDocumentDiagnosticReport {
+                    uri: url,
+                    version,
+                    full_document_diagnostic_report: types::FullDocumentDiagnosticReport {
+                        result_id: Some(result_id),
+                        items: diagnostics,
+                    },
+                },
+            )
+        };
+        items.push(item);
+    }
+
+    Some(types::WorkspaceDiagnosticReportResult::Report(
+        types::WorkspaceDiagnosticReport { items },
+    ))
+}
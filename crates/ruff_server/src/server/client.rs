@@ -0,0 +1,49 @@
+//! Helper types for sending messages back to the client from outside the main event loop
+//! (for example, from a background worker thread).
+
+use lsp_server as lsp;
+use lsp_types as types;
+
+/// A handle that lets background work send notifications and responses to the client.
+#[derive(Clone)]
+pub(crate) struct Client {
+    sender: crossbeam_channel::Sender<lsp::Message>,
+}
+
+impl Client {
+    pub(super) fn new(sender: crossbeam_channel::Sender<lsp::Message>) -> Self {
+        Self { sender }
+    }
+
+    /// Sends a `textDocument/publishDiagnostics`-style (or any other) notification to the client.
+    pub(crate) fn notify<N>(&self, params: N::Params)
+    where
+        N: types::notification::Notification,
+    {
+        let notification = lsp::Notification::new(N::METHOD.to_string(), params);
+        if let Err(err) = self.sender.send(notification.into()) {
+            tracing::error!("Failed to send notification `{}`: {err}", N::METHOD);
+        }
+    }
+
+    /// Sends a response for a request back to the client.
+    pub(crate) fn respond(&self, response: lsp::Response) {
+        let id = response.id.clone();
+        if let Err(err) = self.sender.send(response.into()) {
+            tracing::error!("Failed to send response for request {id}: {err}");
+        }
+    }
+
+    /// Sends a server-to-client request. The caller is responsible for matching the eventual
+    /// response back to this request's id. Returns `false` (after logging) if the request
+    /// couldn't be sent at all, so the caller can react - e.g. by not waiting on a response
+    /// that will never arrive.
+    pub(crate) fn send_request(&self, request: lsp::Request) -> bool {
+        let method = request.method.clone();
+        if let Err(err) = self.sender.send(request.into()) {
+            tracing::error!("Failed to send request `{method}`: {err}");
+            return false;
+        }
+        true
+    }
+}
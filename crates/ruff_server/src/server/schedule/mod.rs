@@ -0,0 +1,466 @@
+//! Implements the event loop thread and the [`Scheduler`], which dispatches [`Task`]s either
+//! synchronously on the main thread or onto a bounded pool of worker threads.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use lsp_server as lsp;
+use lsp_types as types;
+use types::ClientCapabilities;
+
+use super::client::Client;
+use crate::session::Session;
+
+mod task;
+
+pub(crate) use task::Task;
+
+/// Spawns a thread to run the given event loop closure, returning a handle that can be joined
+/// once the connection is torn down.
+pub(crate) fn event_loop_thread(
+    func: impl FnOnce() -> crate::Result<()> + Send + 'static,
+) -> crate::Result<std::thread::JoinHandle<crate::Result<()>>> {
+    Ok(std::thread::Builder::new()
+        .name("ruff:event_loop".into())
+        .spawn(func)?)
+}
+
+/// A lightweight, cloneable flag that a background task can poll to check whether the request
+/// it's serving has been cancelled.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if `$/cancelRequest` has been received for this token's request.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Builds the `$/cancelRequest` error response a background worker should reply with once it
+/// observes its [`CancellationToken`] is set, instead of a result computed from partial work.
+pub(crate) fn cancelled_response(id: lsp::RequestId) -> lsp::Response {
+    lsp::Response::new_err(
+        id,
+        lsp::ErrorCode::RequestCancelled as i32,
+        "request was cancelled".to_string(),
+    )
+}
+
+/// Tracks in-flight requests so that a `$/cancelRequest` notification can be matched back to
+/// the worker task currently serving it.
+#[derive(Debug, Default)]
+struct PendingRequests(Mutex<HashMap<lsp::RequestId, CancellationToken>>);
+
+impl PendingRequests {
+    /// Registers `id` for cancellation, or returns the token already registered for it. Calling
+    /// this more than once for the same `id` (e.g. once before an async progress-creation
+    /// handshake and again once it resolves) must hand back the *same* token - otherwise a
+    /// `$/cancelRequest` racing the handshake would flag a token nobody is polling anymore.
+    fn insert(&self, id: lsp::RequestId) -> CancellationToken {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(id)
+            .or_insert_with(CancellationToken::default)
+            .clone()
+    }
+
+    fn remove(&self, id: &lsp::RequestId) {
+        self.0.lock().unwrap().remove(id);
+    }
+
+    /// Flags the request `id` for cancellation. A cancel for an id that is unknown (never
+    /// registered, or already completed/removed) is a no-op, since the client may race a
+    /// cancellation against the response.
+    fn cancel(&self, id: &lsp::RequestId) {
+        if let Some(token) = self.0.lock().unwrap().get(id) {
+            token.cancel();
+        }
+    }
+}
+
+/// A handle a background [`Task`] uses to report work-done progress. Reporting on a reporter
+/// with no token (the client didn't supply one and doesn't support progress creation) is a
+/// no-op, so callers don't need to branch on client capabilities themselves.
+#[derive(Clone)]
+pub(crate) struct ProgressReporter {
+    client: Client,
+    token: Option<types::ProgressToken>,
+}
+
+impl ProgressReporter {
+    fn send(&self, value: types::WorkDoneProgress) {
+        let Some(token) = self.token.clone() else {
+            return;
+        };
+        self.client
+            .notify::<types::notification::Progress>(types::ProgressParams {
+                token,
+                value: types::ProgressParamsValue::WorkDone(value),
+            });
+    }
+
+    /// Reports `percentage` complete (0-100) with a human-readable `message`.
+    pub(crate) fn report(&self, percentage: u32, message: impl Into<String>) {
+        self.send(types::WorkDoneProgress::Report(
+            types::WorkDoneProgressReport {
+                cancellable: Some(true),
+                message: Some(message.into()),
+                percentage: Some(percentage),
+            },
+        ));
+    }
+
+    fn end(&self) {
+        self.send(types::WorkDoneProgress::End(types::WorkDoneProgressEnd {
+            message: None,
+        }));
+    }
+}
+
+/// Builds the `$/progress` `Begin` payload for a freshly-started piece of work-done progress.
+fn begin_payload(title: String) -> types::WorkDoneProgress {
+    types::WorkDoneProgress::Begin(types::WorkDoneProgressBegin {
+        title,
+        cancellable: Some(true),
+        message: None,
+        percentage: Some(0),
+    })
+}
+
+type ResponseHandler = Box<dyn FnOnce(serde_json::Value) -> Task + Send>;
+
+/// A minimal fixed-size pool of worker threads that background [`Task`]s run on.
+struct ThreadPool {
+    job_sender: crossbeam_channel::Sender<Box<dyn FnOnce() + Send>>,
+    _workers: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    fn new(size: NonZeroUsize) -> Self {
+        let (job_sender, job_receiver) = crossbeam_channel::unbounded::<Box<dyn FnOnce() + Send>>();
+        let workers = (0..size.get())
+            .map(|i| {
+                let job_receiver = job_receiver.clone();
+                std::thread::Builder::new()
+                    .name(format!("ruff:worker:{i}"))
+                    .spawn(move || {
+                        for job in job_receiver {
+                            job();
+                        }
+                    })
+                    .expect("failed to spawn worker thread")
+            })
+            .collect();
+        Self {
+            job_sender,
+            _workers: workers,
+        }
+    }
+
+    fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        self.job_sender
+            .send(Box::new(job))
+            .expect("worker pool should outlive the scheduler");
+    }
+}
+
+/// Dispatches [`Task`]s produced from incoming LSP messages, either running them inline on the
+/// main thread or handing them off to a worker thread, and routes outgoing server-to-client
+/// requests back to their response handlers.
+pub(crate) struct Scheduler<'s> {
+    session: &'s mut Session,
+    client: Client,
+    pool: ThreadPool,
+    pending_requests: Arc<PendingRequests>,
+    response_handlers: HashMap<lsp::RequestId, ResponseHandler>,
+    next_request_id: i32,
+    next_progress_id: u32,
+    supports_work_done_progress: bool,
+}
+
+impl<'s> Scheduler<'s> {
+    pub(crate) fn new(
+        session: &'s mut Session,
+        client_capabilities: &ClientCapabilities,
+        worker_threads: NonZeroUsize,
+        sender: &crossbeam_channel::Sender<lsp::Message>,
+    ) -> Self {
+        let supports_work_done_progress = client_capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or_default();
+        Self {
+            session,
+            client: Client::new(sender.clone()),
+            pool: ThreadPool::new(worker_threads),
+            pending_requests: Arc::default(),
+            response_handlers: HashMap::new(),
+            next_request_id: 0,
+            next_progress_id: 0,
+            supports_work_done_progress,
+        }
+    }
+
+    /// Runs (or schedules) `task`.
+    pub(crate) fn dispatch(&mut self, task: Task) {
+        match task {
+            Task::Nothing => {}
+            Task::Local(f) => {
+                let next = f(self.session, &self.client);
+                self.dispatch(next);
+            }
+            Task::Background { id, progress, run } => {
+                // Registered up front (and idempotently re-insertable) so `$/cancelRequest` can
+                // flag this request even while progress creation is still in flight below.
+                let token = self.pending_requests.insert(id.clone());
+                match progress {
+                    Some((title, client_token)) => {
+                        self.begin_progress_then_spawn(id, token, title, client_token, run);
+                    }
+                    None => {
+                        let reporter = ProgressReporter {
+                            client: self.client.clone(),
+                            token: None,
+                        };
+                        self.spawn_background(id, token, reporter, run);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs `run` on a worker thread, responding to the client and tearing down bookkeeping
+    /// once it finishes.
+    fn spawn_background(
+        &mut self,
+        id: lsp::RequestId,
+        token: CancellationToken,
+        reporter: ProgressReporter,
+        run: impl FnOnce(CancellationToken, ProgressReporter) -> lsp::Response + Send + 'static,
+    ) {
+        let client = self.client.clone();
+        let pending_requests = self.pending_requests.clone();
+        self.pool.execute(move || {
+            let response = run(token, reporter.clone());
+            reporter.end();
+            pending_requests.remove(&id);
+            client.respond(response);
+        });
+    }
+
+    /// Starts work-done progress under `title`, then spawns `run` on a worker thread.
+    ///
+    /// If `client_token` was supplied, the client already knows the token exists, so `Begin` is
+    /// sent and the worker spawned immediately. Otherwise the client must first be asked to
+    /// create the token: the worker is only spawned once that `WorkDoneProgressCreate` request
+    /// resolves (and `Begin` has been sent), so it can never send `Report`/`End` out of order.
+    /// If the client doesn't support work-done progress at all, or the creation request can't
+    /// even be sent, `run` still gets spawned immediately - just without progress.
+    fn begin_progress_then_spawn(
+        &mut self,
+        id: lsp::RequestId,
+        token: CancellationToken,
+        title: String,
+        client_token: Option<types::ProgressToken>,
+        run: impl FnOnce(CancellationToken, ProgressReporter) -> lsp::Response + Send + 'static,
+    ) {
+        if let Some(client_token) = client_token {
+            self.send_progress(client_token.clone(), begin_payload(title));
+            let reporter = ProgressReporter {
+                client: self.client.clone(),
+                token: Some(client_token),
+            };
+            self.spawn_background(id, token, reporter, run);
+            return;
+        }
+
+        if !self.supports_work_done_progress {
+            let reporter = ProgressReporter {
+                client: self.client.clone(),
+                token: None,
+            };
+            self.spawn_background(id, token, reporter, run);
+            return;
+        }
+
+        let progress_token =
+            types::ProgressToken::String(format!("ruff/{}", self.next_progress_id));
+        self.next_progress_id += 1;
+
+        // Built by hand (rather than through `Scheduler::request`) so that `run` is only ever
+        // handed to a worker once we know the creation request was actually sent - never handed
+        // to a response handler that might be discarded unread.
+        let create_request_id = lsp::RequestId::from(self.next_request_id);
+        self.next_request_id += 1;
+        let create_request = lsp::Request::new(
+            create_request_id.clone(),
+            <types::request::WorkDoneProgressCreate as types::request::Request>::METHOD.to_string(),
+            types::WorkDoneProgressCreateParams {
+                token: progress_token.clone(),
+            },
+        );
+
+        if self.client.send_request(create_request) {
+            let client = self.client.clone();
+            let handler: ResponseHandler = Box::new(move |_value| {
+                client.notify::<types::notification::Progress>(types::ProgressParams {
+                    token: progress_token.clone(),
+                    value: types::ProgressParamsValue::WorkDone(begin_payload(title)),
+                });
+                let reporter = ProgressReporter {
+                    client: client.clone(),
+                    token: Some(progress_token),
+                };
+                Task::background(id, move |_cancel_token, _reporter| {
+                    let response = run(token, reporter.clone());
+                    reporter.end();
+                    response
+                })
+            });
+            self.response_handlers.insert(create_request_id, handler);
+        } else {
+            tracing::error!(
+                "Failed to send work-done progress creation request; running without progress"
+            );
+            let reporter = ProgressReporter {
+                client: self.client.clone(),
+                token: None,
+            };
+            self.spawn_background(id, token, reporter, run);
+        }
+    }
+
+    /// Sends a `$/progress` `Begin` notification on an already-known token.
+    fn send_progress(&self, token: types::ProgressToken, begin: types::WorkDoneProgress) {
+        self.client
+            .notify::<types::notification::Progress>(types::ProgressParams {
+                token,
+                value: types::ProgressParamsValue::WorkDone(begin),
+            });
+    }
+
+    /// Handles an incoming `$/cancelRequest` notification, flagging the matching in-flight
+    /// request (if any) so its worker can bail out early.
+    pub(crate) fn cancel(&self, params: types::CancelParams) {
+        let id = match params.id {
+            types::NumberOrString::Number(num) => lsp::RequestId::from(num),
+            types::NumberOrString::String(s) => lsp::RequestId::from(s),
+        };
+        self.pending_requests.cancel(&id);
+    }
+
+    /// Sends a server-to-client request, calling `handler` with the deserialized result once
+    /// the matching response arrives via [`Scheduler::response`].
+    pub(crate) fn request<R>(
+        &mut self,
+        params: R::Params,
+        handler: impl FnOnce(R::Result) -> Task + Send + 'static,
+    ) -> crate::Result<()>
+    where
+        R: types::request::Request,
+    {
+        let id = lsp::RequestId::from(self.next_request_id);
+        self.next_request_id += 1;
+
+        self.response_handlers.insert(
+            id.clone(),
+            Box::new(move |value| match serde_json::from_value(value) {
+                Ok(result) => handler(result),
+                Err(err) => {
+                    tracing::error!("Failed to deserialize response: {err}");
+                    Task::nothing()
+                }
+            }),
+        );
+
+        if self
+            .client
+            .send_request(lsp::Request::new(id.clone(), R::METHOD.to_string(), params))
+        {
+            Ok(())
+        } else {
+            self.response_handlers.remove(&id);
+            Err(anyhow::anyhow!("failed to send `{}` request", R::METHOD).into())
+        }
+    }
+
+    /// Matches an incoming response to the request that triggered it and runs its handler.
+    pub(crate) fn response(&mut self, response: lsp::Response) -> Task {
+        let lsp::Response { id, result, error } = response;
+        let Some(handler) = self.response_handlers.remove(&id) else {
+            tracing::warn!("Received a response for unknown request {id}");
+            return Task::nothing();
+        };
+        if let Some(error) = error {
+            tracing::error!("Client responded to request {id} with an error: {error:?}");
+            return Task::nothing();
+        }
+        handler(result.unwrap_or(serde_json::Value::Null))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_server as lsp;
+
+    use super::PendingRequests;
+
+    #[test]
+    fn cancel_flags_a_registered_request() {
+        let pending = PendingRequests::default();
+        let token = pending.insert(lsp::RequestId::from(1));
+
+        pending.cancel(&lsp::RequestId::from(1));
+
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn cancel_of_unknown_request_is_a_no_op() {
+        let pending = PendingRequests::default();
+        let token = pending.insert(lsp::RequestId::from(1));
+
+        pending.cancel(&lsp::RequestId::from(2));
+
+        assert!(!token.is_cancelled());
+    }
+
+    #[test]
+    fn cancel_after_removal_is_a_no_op() {
+        let pending = PendingRequests::default();
+        let id = lsp::RequestId::from(1);
+        let token = pending.insert(id.clone());
+        pending.remove(&id);
+
+        pending.cancel(&id);
+
+        assert!(!token.is_cancelled());
+    }
+
+    /// The work-done-progress handshake re-inserts the same request id once the
+    /// `WorkDoneProgressCreate` response arrives (see `begin_progress_then_spawn`); that second
+    /// `insert` must hand back the token already registered, not a fresh one nobody's polling.
+    #[test]
+    fn insert_is_idempotent_for_the_same_id() {
+        let pending = PendingRequests::default();
+        let id = lsp::RequestId::from(1);
+
+        let first = pending.insert(id.clone());
+        let second = pending.insert(id);
+
+        first.cancel();
+
+        assert!(second.is_cancelled());
+    }
+}
@@ -0,0 +1,78 @@
+use lsp_server as lsp;
+use lsp_types as types;
+
+use super::CancellationToken;
+use super::ProgressReporter;
+use crate::server::client::Client;
+use crate::session::Session;
+
+type LocalFn = Box<dyn FnOnce(&mut Session, &Client) -> Task + Send + 'static>;
+type BackgroundFn =
+    Box<dyn FnOnce(CancellationToken, ProgressReporter) -> lsp::Response + Send + 'static>;
+
+/// If set, the background task wants work-done progress reported under a title, optionally
+/// against a token the client already supplied in its request (rather than one the server has
+/// to ask the client to create).
+type ProgressSpec = (String, Option<types::ProgressToken>);
+
+/// Describes a unit of work produced by [`api::request`](super::super::api::request) or
+/// [`api::notification`](super::super::api::notification) for the [`Scheduler`](super::Scheduler)
+/// to run.
+pub(crate) enum Task {
+    /// Do nothing. Used for notifications/responses that don't need any follow-up work.
+    Nothing,
+    /// Run synchronously on the main event loop thread, with exclusive access to the [`Session`].
+    /// Its return value is itself dispatched, so a local step can hand further work (e.g. a
+    /// [`Task::background`] job built from data it read out of the session) back to the
+    /// scheduler; return [`Task::nothing`] if there's none.
+    Local(LocalFn),
+    /// Run on a background worker thread. The closure is handed a [`CancellationToken`] it
+    /// should poll at coarse checkpoints and a [`ProgressReporter`] it can report chunks of
+    /// work through; once it returns, its response is sent to the client, any work-done
+    /// progress is closed out, and the request is removed from the pending-requests registry.
+    Background {
+        id: lsp::RequestId,
+        progress: Option<ProgressSpec>,
+        run: BackgroundFn,
+    },
+}
+
+impl Task {
+    pub(crate) fn nothing() -> Self {
+        Self::Nothing
+    }
+
+    pub(crate) fn local(f: impl FnOnce(&mut Session, &Client) -> Task + Send + 'static) -> Self {
+        Self::Local(Box::new(f))
+    }
+
+    /// Schedules `f` to run on a worker thread. `id` is the id of the request being served,
+    /// used both to register the request for cancellation and to tag the eventual response.
+    pub(crate) fn background(
+        id: lsp::RequestId,
+        f: impl FnOnce(CancellationToken, ProgressReporter) -> lsp::Response + Send + 'static,
+    ) -> Self {
+        Self::Background {
+            id,
+            progress: None,
+            run: Box::new(f),
+        }
+    }
+
+    /// Like [`Task::background`], but also requests work-done progress under `title`. If the
+    /// client already supplied a `work_done_token` on its request, reuse it; otherwise the
+    /// scheduler asks the client to create one, and silently reports nothing if the client
+    /// doesn't support work-done progress at all.
+    pub(crate) fn background_with_progress(
+        id: lsp::RequestId,
+        title: impl Into<String>,
+        client_token: Option<types::ProgressToken>,
+        f: impl FnOnce(CancellationToken, ProgressReporter) -> lsp::Response + Send + 'static,
+    ) -> Self {
+        Self::Background {
+            id,
+            progress: Some((title.into(), client_token)),
+            run: Box::new(f),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! A server-owned fallback for `workspace/didChangeWatchedFiles` dynamic registration, for
+//! clients that don't support registering their own filesystem watchers.
+//!
+//! When [`try_register_capabilities`](super::Server::try_register_capabilities) can't rely on
+//! the client to watch `ruff.toml`/`pyproject.toml` for us, we watch them ourselves with the
+//! `notify` crate and synthesize the same `DidChangeWatchedFiles` notification the client would
+//! have sent, so the rest of the server can't tell the difference.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use lsp_types as types;
+use notify::RecursiveMode;
+use notify::Watcher as _;
+
+/// How long to wait after the last raw filesystem event before flushing a batch to the server.
+/// Editors and package managers tend to touch a config file multiple times in quick succession
+/// (write, then chmod, then rename-into-place); this collapses those into one change event.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches every workspace folder for changes to `ruff.toml`, `.ruff.toml`, and
+/// `pyproject.toml`, the same glob set the client-side registration asks for.
+pub(crate) struct ServerWatcher {
+    // Kept alive for as long as the watcher should keep running; never read directly.
+    _watcher: notify::RecommendedWatcher,
+    changes: crossbeam_channel::Receiver<types::DidChangeWatchedFilesParams>,
+}
+
+impl ServerWatcher {
+    pub(crate) fn spawn(roots: Vec<PathBuf>) -> notify::Result<Self> {
+        let (raw_sender, raw_receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(raw_sender)?;
+        for root in &roots {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+
+        let (sender, changes) = crossbeam_channel::unbounded();
+        std::thread::Builder::new()
+            .name("ruff:fs-watcher".into())
+            .spawn(move || Self::debounce_loop(&raw_receiver, &sender))
+            .expect("failed to spawn filesystem watcher thread");
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// The channel the event loop selects on alongside the LSP connection; each item is ready
+    /// to be synthesized into a `workspace/didChangeWatchedFiles` notification.
+    pub(crate) fn changes(
+        &self,
+    ) -> &crossbeam_channel::Receiver<types::DidChangeWatchedFilesParams> {
+        &self.changes
+    }
+
+    fn debounce_loop(
+        raw_receiver: &mpsc::Receiver<notify::Result<notify::Event>>,
+        sender: &crossbeam_channel::Sender<types::DidChangeWatchedFilesParams>,
+    ) {
+        let mut pending = Vec::new();
+        loop {
+            match raw_receiver.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) => pending.extend(watched_file_events(&event)),
+                Ok(Err(err)) => tracing::warn!("Filesystem watcher error: {err}"),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if pending.is_empty() {
+                        continue;
+                    }
+                    let changes = std::mem::take(&mut pending);
+                    if sender
+                        .send(types::DidChangeWatchedFilesParams { changes })
+                        .is_err()
+                    {
+                        return; // the event loop shut down; nothing left to notify.
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// Converts a raw `notify` event into LSP `FileEvent`s, keeping only paths that match the
+/// `**/.?ruff.toml` / `**/pyproject.toml` glob set we'd otherwise ask the client to watch.
+fn watched_file_events(event: &notify::Event) -> Vec<types::FileEvent> {
+    let change_type = match event.kind {
+        notify::EventKind::Create(_) => types::FileChangeType::CREATED,
+        notify::EventKind::Remove(_) => types::FileChangeType::DELETED,
+        _ => types::FileChangeType::CHANGED,
+    };
+
+    event
+        .paths
+        .iter()
+        .filter(|path| is_watched_config_file(path))
+        .filter_map(|path| types::Url::from_file_path(path).ok())
+        .map(|uri| types::FileEvent {
+            uri,
+            typ: change_type,
+        })
+        .collect()
+}
+
+fn is_watched_config_file(path: &Path) -> bool {
+    matches!(
+        path.file_name().and_then(|name| name.to_str()),
+        Some("ruff.toml" | ".ruff.toml" | "pyproject.toml")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_watched_config_file;
+    use std::path::Path;
+
+    #[test]
+    fn recognizes_every_watched_config_file_name() {
+        assert!(is_watched_config_file(Path::new("/project/ruff.toml")));
+        assert!(is_watched_config_file(Path::new("/project/.ruff.toml")));
+        assert!(is_watched_config_file(Path::new("/project/pyproject.toml")));
+    }
+
+    #[test]
+    fn ignores_unrelated_files() {
+        assert!(!is_watched_config_file(Path::new("/project/main.py")));
+        assert!(!is_watched_config_file(Path::new("/project/setup.cfg")));
+        assert!(!is_watched_config_file(Path::new("/project/toml.ruff")));
+    }
+}
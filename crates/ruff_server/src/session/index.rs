@@ -0,0 +1,89 @@
+//! Per-document derived state that isn't part of the document's text itself: open document
+//! version and the diagnostic result id used to answer `workspace/diagnostic` cheaply.
+
+use std::collections::HashMap;
+
+use lsp_types::Url;
+
+#[derive(Debug, Default)]
+pub(super) struct Index {
+    documents: HashMap<Url, DocumentState>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct DocumentState {
+    /// The version from the last `textDocument/didChange`, or `None` if none was received.
+    version: Option<i32>,
+    /// Bumped every time this document's diagnostics are invalidated, so a client's cached
+    /// `previousResultId` can be compared against the current state without resending results.
+    result_id: u64,
+}
+
+impl Index {
+    pub(super) fn document_version(&self, url: &Url) -> Option<i32> {
+        self.documents.get(url)?.version
+    }
+
+    pub(super) fn set_document_version(&mut self, url: &Url, version: i32) {
+        self.documents.entry(url.clone()).or_default().version = Some(version);
+    }
+
+    /// The diagnostic result id for `url`, folded together with `settings_generation` so that a
+    /// workspace settings change invalidates every affected document's result id too, without
+    /// needing to walk and bump every document individually.
+    pub(super) fn diagnostic_result_id(&mut self, url: &Url, settings_generation: u64) -> String {
+        let result_id = self.documents.entry(url.clone()).or_default().result_id;
+        format!("{result_id}-{settings_generation}")
+    }
+
+    /// Bumps `url`'s diagnostic result id, leaving every other document's entry untouched.
+    pub(super) fn invalidate(&mut self, url: &Url) {
+        self.documents.entry(url.clone()).or_default().result_id += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Index;
+    use lsp_types::Url;
+
+    fn url(path: &str) -> Url {
+        Url::parse(&format!("file:///{path}")).unwrap()
+    }
+
+    #[test]
+    fn invalidate_changes_the_result_id() {
+        let mut index = Index::default();
+        let a = url("a.py");
+
+        let before = index.diagnostic_result_id(&a, 0);
+        index.invalidate(&a);
+        let after = index.diagnostic_result_id(&a, 0);
+
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn invalidate_does_not_affect_other_documents() {
+        let mut index = Index::default();
+        let a = url("a.py");
+        let b = url("b.py");
+
+        let before = index.diagnostic_result_id(&b, 0);
+        index.invalidate(&a);
+        let after = index.diagnostic_result_id(&b, 0);
+
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn settings_generation_is_folded_into_the_result_id() {
+        let mut index = Index::default();
+        let a = url("a.py");
+
+        let before = index.diagnostic_result_id(&a, 0);
+        let after = index.diagnostic_result_id(&a, 1);
+
+        assert_ne!(before, after);
+    }
+}
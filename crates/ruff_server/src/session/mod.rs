@@ -0,0 +1,191 @@
+//! Data model, state management, and configuration resolution for the server.
+
+use std::collections::BTreeMap;
+
+use lsp_types::ClientCapabilities;
+use lsp_types::Diagnostic;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::Range;
+use lsp_types::ServerCapabilities;
+use lsp_types::Url;
+
+use crate::PositionEncoding;
+
+mod index;
+
+/// The core data structure backing the LSP server. `Session` holds the state for every
+/// workspace folder the client has opened, as well as in-memory state for every document
+/// the client has opened within those folders.
+#[derive(Debug)]
+pub struct Session {
+    /// Workspace folders, ordered by URL, each mapped to their settings state.
+    workspaces: BTreeMap<Url, WorkspaceSettings>,
+    /// Per-document derived state (open document version, cached diagnostic result id, ...),
+    /// keyed by document URL. An entry can exist for a document the client hasn't opened, since
+    /// `workspace/diagnostic` computes results for every Python file in the workspace.
+    index: index::Index,
+    position_encoding: PositionEncoding,
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct WorkspaceSettings {
+    /// Bumped every time this folder's configuration files change, so any resolved-settings
+    /// cache keyed on it is known to be stale.
+    settings_generation: u64,
+}
+
+impl Session {
+    pub fn new(
+        client_capabilities: &ClientCapabilities,
+        _server_capabilities: &ServerCapabilities,
+        workspaces: &[Url],
+    ) -> crate::Result<Self> {
+        let position_encoding = client_capabilities
+            .general
+            .as_ref()
+            .and_then(|general_capabilities| general_capabilities.position_encodings.as_ref())
+            .and_then(|encodings| {
+                encodings
+                    .iter()
+                    .filter_map(|encoding| PositionEncoding::try_from(encoding).ok())
+                    .max()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            workspaces: workspaces
+                .iter()
+                .map(|url| (url.clone(), WorkspaceSettings::default()))
+                .collect(),
+            index: index::Index::default(),
+            position_encoding,
+        })
+    }
+
+    pub(crate) fn position_encoding(&self) -> PositionEncoding {
+        self.position_encoding
+    }
+
+    /// Returns the filesystem root of every workspace folder the session knows about. Used to
+    /// seed the fallback filesystem watcher.
+    pub(crate) fn workspace_roots(&self) -> Vec<std::path::PathBuf> {
+        self.workspaces
+            .keys()
+            .filter_map(|folder| folder.to_file_path().ok())
+            .collect()
+    }
+
+    /// Returns every Python file the session knows about, across all workspace folders, by
+    /// walking each folder's directory tree (respecting `.gitignore`, like the linter CLI does).
+    pub(crate) fn workspace_python_files(&self) -> Vec<Url> {
+        self.workspaces
+            .keys()
+            .filter_map(|folder| folder.to_file_path().ok())
+            .flat_map(|root| {
+                ignore::WalkBuilder::new(root)
+                    .build()
+                    .filter_map(std::result::Result::ok)
+                    .filter(|entry| {
+                        entry
+                            .path()
+                            .extension()
+                            .is_some_and(|ext| ext == "py" || ext == "pyi")
+                    })
+                    .filter_map(|entry| Url::from_file_path(entry.path()).ok())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The version recorded for `url` by its last `textDocument/didChange`, or `None` if none
+    /// was received. `didOpen` and `didClose` are not tracked, so a closed document keeps its
+    /// last version and a document opened but not yet changed has none.
+    pub(crate) fn document_version(&self, url: &Url) -> Option<i32> {
+        self.index.document_version(url)
+    }
+
+    /// The diagnostic result id currently cached for `url`, computing one if this is the first
+    /// time we've seen the document. Folds in the owning workspace folder's settings
+    /// generation, so a config file change invalidates every document's result id under that
+    /// folder without the session having to walk and invalidate them one by one.
+    pub(crate) fn diagnostic_result_id(&mut self, url: &Url) -> String {
+        let settings_generation = self.settings_generation_for(url);
+        self.index.diagnostic_result_id(url, settings_generation)
+    }
+
+    /// Records `version` as the open document version for `url`.
+    pub(crate) fn set_document_version(&mut self, url: &Url, version: i32) {
+        self.index.set_document_version(url, version);
+    }
+
+    /// Bumps `url`'s diagnostic result id, leaving every other document's cached state
+    /// untouched. The session holds no parsed-AST or per-document settings cache, so the result
+    /// id is the only per-document state this evicts.
+    pub(crate) fn invalidate(&mut self, url: &Url) {
+        self.index.invalidate(url);
+    }
+
+    /// Bumps the settings generation of `folder` alone, leaving every other workspace folder's
+    /// settings untouched. Documents under `folder` get new diagnostic result ids through that
+    /// generation (see [`Session::diagnostic_result_id`]); no other document's result id changes.
+    pub(crate) fn invalidate_settings(&mut self, folder: &Url) {
+        if let Some(settings) = self.workspaces.get_mut(folder) {
+            settings.settings_generation += 1;
+        }
+    }
+
+    /// Invalidates the settings of whichever workspace folder contains `config_file`, e.g. a
+    /// `ruff.toml` or `pyproject.toml` that just changed on disk.
+    pub(crate) fn invalidate_settings_for(&mut self, config_file: &Url) {
+        let Some(folder) = self.workspace_folder_for(config_file) else {
+            tracing::debug!("No workspace folder owns changed config file {config_file}");
+            return;
+        };
+        self.invalidate_settings(&folder);
+    }
+
+    /// The settings generation of whichever workspace folder owns `url`, or `0` if no folder
+    /// does. Used to fold settings invalidation into a document's diagnostic result id.
+    fn settings_generation_for(&self, url: &Url) -> u64 {
+        self.workspace_folder_for(url)
+            .and_then(|folder| self.workspaces.get(&folder))
+            .map_or(0, |settings| settings.settings_generation)
+    }
+
+    /// The workspace folder that owns `url` - the longest-matching folder prefix, since nested
+    /// workspace folders are possible - or `None` if no folder contains it.
+    fn workspace_folder_for(&self, url: &Url) -> Option<Url> {
+        self.workspaces
+            .keys()
+            .filter(|folder| {
+                url.as_str()
+                    .strip_prefix(folder.as_str())
+                    .is_some_and(|rest| {
+                        rest.is_empty() || rest.starts_with('/') || folder.as_str().ends_with('/')
+                    })
+            })
+            .max_by_key(|folder| folder.as_str().len())
+            .cloned()
+    }
+}
+
+/// Computes the diagnostics for a single file: the per-file step of `workspace/diagnostic`.
+/// It's a plain function, not a [`Session`] method, since it doesn't need (and `workspace/
+/// diagnostic`'s background worker can't hold) a session reference.
+///
+/// Not yet implemented: `workspace/diagnostic`'s result-id caching and `Unchanged`/`Full`
+/// switching are fully wired, but no lint pass exists in this tree yet. Rather than report an
+/// empty (and misleadingly "clean") result, every file gets a single informational diagnostic
+/// saying so - callers must not treat this as "no lint errors".
+pub(crate) fn diagnostics_for(url: &Url) -> Vec<Diagnostic> {
+    tracing::debug!("Diagnostics for {url} are not yet implemented; reporting a placeholder");
+    vec![Diagnostic {
+        range: Range::default(),
+        severity: Some(DiagnosticSeverity::INFORMATION),
+        source: Some("ruff".to_string()),
+        message: "Ruff's lint pass is not implemented in this build; no diagnostics were \
+                  computed for this file."
+            .to_string(),
+        ..Default::default()
+    }]
+}